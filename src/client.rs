@@ -1,28 +1,28 @@
+use std::error::Error as StdError;
 use std::fmt;
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use hyper::client::connect::{Connected, Destination, HttpConnector};
+use hyper::client::connect::{Connected, Connection, Destination, HttpConnector};
 pub use native_tls::Error;
-use tokio::net::TcpStream;
 use tokio_io::{AsyncRead, AsyncWrite};
 use tokio_tls::TlsConnector;
-//use tokio_net::tcp::TcpStream;
 use tower_service::Service;
 
 use crate::stream::MaybeHttpsStream;
 
 /// A Connector for the `https` scheme.
 #[derive(Clone)]
-pub struct HttpsConnector {
+pub struct HttpsConnector<T = HttpConnector> {
     force_https: bool,
-    http: HttpConnector,
+    http: T,
     tls: TlsConnector,
+    override_server_name: Option<String>,
 }
 
-impl HttpsConnector {
+impl HttpsConnector<HttpConnector> {
     /// Construct a new HttpsConnector.
     ///
     /// Takes number of DNS worker threads.
@@ -48,29 +48,169 @@ impl HttpsConnector {
     }
 }
 
-impl From<(HttpConnector, TlsConnector)> for HttpsConnector {
-    fn from(args: (HttpConnector, TlsConnector)) -> HttpsConnector {
+#[cfg(unix)]
+impl<T> HttpsConnector<T>
+where
+    T: Service<Destination>,
+    T::Response: AsyncRead + AsyncWrite + Connection + Send + Unpin + 'static,
+{
+    /// Connect to a `unix://` (or `unixs://`) destination over a Unix domain
+    /// socket, wrapping it in TLS when `use_tls` is set.
+    fn call_unix(&mut self, dst: Destination, use_tls: bool) -> HttpsConnecting<T::Response> {
+        let path = dst.host().to_owned();
+        let tls = self.tls.clone();
+        let server_name = self.override_server_name.clone();
+
+        let fut = async move {
+            let unix = tokio::net::UnixStream::connect(&path).await?;
+            let mut connected = Connected::new();
+
+            let maybe = if use_tls {
+                let name = server_name.unwrap_or(path);
+                let tls = tls
+                    .connect(&name, unix)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                // Mirror the TCP path: honour an ALPN-negotiated HTTP/2.
+                if let Ok(Some(proto)) = tls.get_ref().negotiated_alpn() {
+                    if proto == b"h2" {
+                        connected = connected.negotiated_h2();
+                    }
+                }
+                MaybeHttpsStream::HttpsUnix(tls)
+            } else {
+                MaybeHttpsStream::Unix(unix)
+            };
+
+            Ok((maybe, connected))
+        };
+
+        HttpsConnecting(Box::pin(fut))
+    }
+}
+
+impl<T> HttpsConnector<T> {
+    /// Override the server name used for SNI and certificate verification.
+    ///
+    /// When set, `name` is handed to the TLS handshake instead of the URI
+    /// host. The URI host is still used to open the underlying connection,
+    /// so this is the way to reach a backend by IP or load-balancer address
+    /// while still validating against a known certificate name.
+    pub fn set_server_name(&mut self, name: impl Into<String>) {
+        self.override_server_name = Some(name.into());
+    }
+}
+
+impl<T> From<(T, TlsConnector)> for HttpsConnector<T> {
+    fn from(args: (T, TlsConnector)) -> HttpsConnector<T> {
         HttpsConnector {
             force_https: false,
             http: args.0,
             tls: args.1,
+            override_server_name: None,
+        }
+    }
+}
+
+/// A builder for a [`HttpsConnector`].
+///
+/// Unlike `HttpsConnector::new()` or `From`, this lets you configure the
+/// TLS settings that matter most — root certificates, the ALPN protocol
+/// list, the verified server name, and whether plain HTTP is allowed — from
+/// a single place without threading a `native_tls::TlsConnectorBuilder`
+/// through `From` yourself.
+pub struct HttpsConnectorBuilder<T = HttpConnector> {
+    http: T,
+    tls: native_tls::TlsConnectorBuilder,
+    https_only: bool,
+    override_server_name: Option<String>,
+}
+
+impl HttpsConnectorBuilder<HttpConnector> {
+    /// Start building a connector over hyper's default `HttpConnector`.
+    pub fn new() -> Self {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        HttpsConnectorBuilder::with_connector(http)
+    }
+}
+
+impl Default for HttpsConnectorBuilder<HttpConnector> {
+    fn default() -> Self {
+        HttpsConnectorBuilder::new()
+    }
+}
+
+impl<T> HttpsConnectorBuilder<T> {
+    /// Start building a connector over the given transport connector.
+    pub fn with_connector(http: T) -> Self {
+        HttpsConnectorBuilder {
+            http,
+            tls: native_tls::TlsConnector::builder(),
+            https_only: false,
+            override_server_name: None,
         }
     }
+
+    /// Add a root certificate trusted when verifying the peer.
+    pub fn add_root_certificate(mut self, cert: native_tls::Certificate) -> Self {
+        self.tls.add_root_certificate(cert);
+        self
+    }
+
+    /// Set the ALPN protocols to advertise during the handshake, in order of
+    /// preference (eg `&["h2", "http/1.1"]`).
+    pub fn request_alpns(mut self, protocols: &[&str]) -> Self {
+        self.tls.request_alpns(protocols);
+        self
+    }
+
+    /// Use `name` as the SNI hostname and certificate-verification name
+    /// instead of the URI host.
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.override_server_name = Some(name.into());
+        self
+    }
+
+    /// Refuse plain `http` destinations; connecting over plain HTTP fails.
+    /// The `unix` and `unixs` schemes are not affected by this setting.
+    pub fn https_only(mut self, enable: bool) -> Self {
+        self.https_only = enable;
+        self
+    }
+
+    /// Build the configured `HttpsConnector`.
+    pub fn build(self) -> Result<HttpsConnector<T>, Error> {
+        let tls = self.tls.build()?;
+        Ok(HttpsConnector {
+            force_https: self.https_only,
+            http: self.http,
+            tls: tls.into(),
+            override_server_name: self.override_server_name,
+        })
+    }
 }
 
-impl fmt::Debug for HttpsConnector {
+impl<T: fmt::Debug> fmt::Debug for HttpsConnector<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("HttpsConnector")
             .field("force_https", &self.force_https)
             .field("http", &self.http)
+            .field("override_server_name", &self.override_server_name)
             .finish()
     }
 }
 
-impl Service<Destination> for HttpsConnector {
-    type Response = (MaybeHttpsStream<TcpStream>, Connected);
+impl<T> Service<Destination> for HttpsConnector<T>
+where
+    T: Service<Destination>,
+    T::Response: AsyncRead + AsyncWrite + Connection + Send + Unpin + 'static,
+    T::Future: Send + 'static,
+    T::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+    type Response = (MaybeHttpsStream<T::Response>, Connected);
     type Error = io::Error;
-    type Future = HttpsConnecting<TcpStream>;
+    type Future = HttpsConnecting<T::Response>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         // For now, always ready.
@@ -82,6 +222,20 @@ impl Service<Destination> for HttpsConnector {
 
     fn call(&mut self, dst: Destination) -> Self::Future {
         let is_https = dst.scheme() == "https";
+
+        // Route `unix://` destinations straight to the socket connector,
+        // optionally wrapping the socket in TLS.
+        #[cfg(unix)]
+        {
+            // `unix://` is a plaintext socket, `unixs://` tunnels TLS over it.
+            // Keep this independent of `force_https`, which only governs the
+            // HTTP(S) transport.
+            let scheme = dst.scheme();
+            if scheme == "unix" || scheme == "unixs" {
+                return self.call_unix(dst, scheme == "unixs");
+            }
+        }
+
         // Early abort if HTTPS is forced but can't be used
         if !is_https && self.force_https {
             let err = io::Error::new(
@@ -91,12 +245,16 @@ impl Service<Destination> for HttpsConnector {
             return HttpsConnecting(Box::pin(async { Err(err) }));
         }
 
-        let host = dst.host().to_owned();
+        let host = self
+            .override_server_name
+            .clone()
+            .unwrap_or_else(|| dst.host().to_owned());
         let connecting = self.http.call(dst);
         let tls = self.tls.clone();
 
         let fut = async move {
-            let (tcp, connected) = connecting.await.map_err(|e| {
+            let (inner, mut connected) = connecting.await.map_err(|e| {
+                let e: Box<dyn StdError + Send + Sync> = e.into();
                 io::Error::new(
                     io::ErrorKind::Other,
                     format!("HTTP Connection failed: {:?}", e),
@@ -105,12 +263,19 @@ impl Service<Destination> for HttpsConnector {
 
             let maybe = if is_https {
                 let tls = tls
-                    .connect(&host, tcp)
+                    .connect(&host, inner)
                     .await
                     .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                // If the server negotiated HTTP/2 over ALPN, let hyper know so
+                // it can speak h2 without a separate upgrade.
+                if let Ok(Some(proto)) = tls.get_ref().negotiated_alpn() {
+                    if proto == b"h2" {
+                        connected = connected.negotiated_h2();
+                    }
+                }
                 MaybeHttpsStream::Https(tls)
             } else {
-                MaybeHttpsStream::Http(tcp)
+                MaybeHttpsStream::Http(inner)
             };
 
             Ok((maybe, connected))
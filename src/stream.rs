@@ -0,0 +1,130 @@
+use std::fmt;
+use std::io;
+use std::mem::MaybeUninit;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use hyper::client::connect::{Connected, Connection};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_tls::TlsStream;
+
+/// A stream that might be protected with TLS.
+pub enum MaybeHttpsStream<T> {
+    /// A stream over plain text.
+    Http(T),
+    /// A stream protected with TLS.
+    Https(TlsStream<T>),
+    /// A stream over a Unix domain socket.
+    #[cfg(unix)]
+    Unix(UnixStream),
+    /// A Unix domain socket protected with TLS.
+    #[cfg(unix)]
+    HttpsUnix(TlsStream<UnixStream>),
+}
+
+impl<T> fmt::Debug for MaybeHttpsStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MaybeHttpsStream::Http(..) => f.pad("Http(..)"),
+            MaybeHttpsStream::Https(..) => f.pad("Https(..)"),
+            #[cfg(unix)]
+            MaybeHttpsStream::Unix(..) => f.pad("Unix(..)"),
+            #[cfg(unix)]
+            MaybeHttpsStream::HttpsUnix(..) => f.pad("HttpsUnix(..)"),
+        }
+    }
+}
+
+impl<T> From<T> for MaybeHttpsStream<T> {
+    fn from(inner: T) -> Self {
+        MaybeHttpsStream::Http(inner)
+    }
+}
+
+impl<T> From<TlsStream<T>> for MaybeHttpsStream<T> {
+    fn from(inner: TlsStream<T>) -> Self {
+        MaybeHttpsStream::Https(inner)
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Connection + Unpin> Connection for MaybeHttpsStream<T> {
+    fn connected(&self) -> Connected {
+        match self {
+            MaybeHttpsStream::Http(s) => s.connected(),
+            MaybeHttpsStream::Https(s) => s.get_ref().connected(),
+            #[cfg(unix)]
+            MaybeHttpsStream::Unix(_) => Connected::new(),
+            #[cfg(unix)]
+            MaybeHttpsStream::HttpsUnix(_) => Connected::new(),
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for MaybeHttpsStream<T> {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [MaybeUninit<u8>]) -> bool {
+        match self {
+            MaybeHttpsStream::Http(s) => s.prepare_uninitialized_buffer(buf),
+            MaybeHttpsStream::Https(s) => s.prepare_uninitialized_buffer(buf),
+            #[cfg(unix)]
+            MaybeHttpsStream::Unix(s) => s.prepare_uninitialized_buffer(buf),
+            #[cfg(unix)]
+            MaybeHttpsStream::HttpsUnix(s) => s.prepare_uninitialized_buffer(buf),
+        }
+    }
+
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match Pin::get_mut(self) {
+            MaybeHttpsStream::Http(s) => Pin::new(s).poll_read(cx, buf),
+            MaybeHttpsStream::Https(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(unix)]
+            MaybeHttpsStream::Unix(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(unix)]
+            MaybeHttpsStream::HttpsUnix(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> AsyncWrite for MaybeHttpsStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match Pin::get_mut(self) {
+            MaybeHttpsStream::Http(s) => Pin::new(s).poll_write(cx, buf),
+            MaybeHttpsStream::Https(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(unix)]
+            MaybeHttpsStream::Unix(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(unix)]
+            MaybeHttpsStream::HttpsUnix(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match Pin::get_mut(self) {
+            MaybeHttpsStream::Http(s) => Pin::new(s).poll_flush(cx),
+            MaybeHttpsStream::Https(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(unix)]
+            MaybeHttpsStream::Unix(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(unix)]
+            MaybeHttpsStream::HttpsUnix(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match Pin::get_mut(self) {
+            MaybeHttpsStream::Http(s) => Pin::new(s).poll_shutdown(cx),
+            MaybeHttpsStream::Https(s) => Pin::new(s).poll_shutdown(cx),
+            #[cfg(unix)]
+            MaybeHttpsStream::Unix(s) => Pin::new(s).poll_shutdown(cx),
+            #[cfg(unix)]
+            MaybeHttpsStream::HttpsUnix(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}